@@ -0,0 +1,21 @@
+use clap::{Parser, Subcommand};
+
+/// cfddns
+/// A Cloudflare dynamic DNS daemon - keeps A/AAAA records in sync with this
+/// host's public IP address.
+#[derive(Parser, Debug)]
+#[command(name = "cfddns", version, about)]
+pub struct Cli {
+    /// Defaults to `run` so existing deployments invoking the bare binary
+    /// keep starting the daemon.
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Run the long-running update daemon.
+    Run,
+    /// Print the records Cloudflare currently has under each configured record's name.
+    List,
+}
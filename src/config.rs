@@ -0,0 +1,195 @@
+use serde::Deserialize;
+use std::env;
+use std::fs;
+use std::path::Path;
+
+use crate::split_subdomain;
+
+/// Default location for the config file, relative to the working directory.
+/// Overridable via the `CONFIG_FILE` environment variable.
+const DEFAULT_CONFIG_PATH: &str = "cfddns.toml";
+
+#[derive(Debug)]
+pub enum ConfigError {
+    ReadError { path: String, err: std::io::Error },
+    ParseError { path: String, err: toml::de::Error },
+    MissingEnvVar { var: &'static str },
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::ReadError { path, err } => write!(f, "Failed to read config file {path}: {err}"),
+            ConfigError::ParseError { path, err } => write!(f, "Failed to parse config file {path}: {err}"),
+            ConfigError::MissingEnvVar { var } => write!(f, "Environment variable {var} must be set"),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Top-level configuration: a list of zones, each managed with its own
+/// Cloudflare API token and set of records.
+#[derive(Debug, Deserialize)]
+pub struct Config {
+    pub zones: Vec<ZoneConfig>,
+    #[serde(default)]
+    pub ip_sources: IpSourcesConfig,
+}
+
+/// Where to source the current public IP from, per IP version.
+#[derive(Debug, Default, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum IpSource {
+    /// Query the external reflector services and require consensus among them.
+    #[default]
+    Reflector,
+    /// Read the address directly off a named local network interface.
+    Interface {
+        name: String,
+        /// Accept link-local (`fe80::/10`), RFC1918, and ULA (`fc00::/7`)
+        /// addresses from this interface instead of filtering them out.
+        /// Useful on an interface that's never going to carry a public
+        /// address itself (e.g. behind another NAT layer you still want
+        /// tracked).
+        #[serde(default)]
+        allow_private: bool,
+    },
+}
+
+/// Per-IP-version `IpSource` selection. Defaults to `reflector` for both,
+/// matching the original external-provider-only behavior.
+#[derive(Debug, Deserialize)]
+pub struct IpSourcesConfig {
+    #[serde(default)]
+    pub v4: IpSource,
+    #[serde(default)]
+    pub v6: IpSource,
+    /// Minimum number of reflector providers that must agree on an address
+    /// before it's trusted. Only relevant when a version's source is `reflector`.
+    #[serde(default = "default_min_agreement")]
+    pub min_agreement: usize,
+}
+
+impl Default for IpSourcesConfig {
+    fn default() -> Self {
+        IpSourcesConfig {
+            v4: IpSource::default(),
+            v6: IpSource::default(),
+            min_agreement: default_min_agreement(),
+        }
+    }
+}
+
+fn default_min_agreement() -> usize {
+    2
+}
+
+/// A single Cloudflare zone (domain) to manage, identified either by its
+/// zone name (e.g. "example.com") or a pre-resolved zone ID.
+#[derive(Debug, Deserialize)]
+pub struct ZoneConfig {
+    pub zone: String,
+    pub token: String,
+    pub records: Vec<RecordConfig>,
+}
+
+/// A single subdomain to keep up to date, and which record types it wants.
+/// Use an empty string for `subdomain` to target the zone apex.
+#[derive(Debug, Deserialize)]
+pub struct RecordConfig {
+    pub subdomain: String,
+    #[serde(default = "default_true")]
+    pub a: bool,
+    #[serde(default = "default_true")]
+    pub aaaa: bool,
+    /// Whether the record should sit behind Cloudflare's proxy (orange cloud).
+    #[serde(default)]
+    pub proxied: bool,
+    /// Record TTL in seconds. Use `1` for Cloudflare's "auto" TTL.
+    #[serde(default = "default_ttl")]
+    pub ttl: u32,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_ttl() -> u32 {
+    600
+}
+
+impl Config {
+    /// from_file
+    /// Loads and parses a `Config` from a TOML file at the given path.
+    pub fn from_file(path: &Path) -> Result<Config, ConfigError> {
+        let contents = fs::read_to_string(path)
+            .map_err(|err| ConfigError::ReadError { path: path.display().to_string(), err })?;
+
+        toml::from_str(&contents)
+            .map_err(|err| ConfigError::ParseError { path: path.display().to_string(), err })
+    }
+
+    /// from_env
+    /// Builds a single-zone `Config` from the legacy `DOMAIN`/`SUBDOMAINS`/`TOKEN`
+    /// environment variables, so existing deployments keep working without a
+    /// config file. Every subdomain requests both A and AAAA records, matching
+    /// the old unconditional behavior.
+    pub fn from_env() -> Result<Config, ConfigError> {
+        let domain = env::var("DOMAIN").map_err(|_| ConfigError::MissingEnvVar { var: "DOMAIN" })?;
+        let token = env::var("TOKEN").map_err(|_| ConfigError::MissingEnvVar { var: "TOKEN" })?;
+        let env_subdomains =
+            env::var("SUBDOMAINS").map_err(|_| ConfigError::MissingEnvVar { var: "SUBDOMAINS" })?;
+
+        let records = split_subdomain(&env_subdomains)
+            .into_iter()
+            .map(|subdomain| RecordConfig {
+                subdomain: subdomain.to_string(),
+                a: true,
+                aaaa: true,
+                proxied: false,
+                ttl: default_ttl(),
+            })
+            .collect();
+
+        Ok(Config {
+            zones: vec![ZoneConfig { zone: domain, token, records }],
+            ip_sources: IpSourcesConfig::default(),
+        })
+    }
+
+    /// load
+    /// Loads the config file pointed to by `CONFIG_FILE`, if set, or the
+    /// default `cfddns.toml` otherwise. An explicitly set `CONFIG_FILE` that
+    /// fails to read is propagated as an error rather than silently falling
+    /// back - only the *default* path falls back to the legacy environment
+    /// variables when it's absent.
+    pub fn load() -> Result<Config, ConfigError> {
+        match env::var("CONFIG_FILE") {
+            Ok(config_path) => Config::from_file(Path::new(&config_path)),
+            Err(_) => {
+                let path = Path::new(DEFAULT_CONFIG_PATH);
+
+                if path.exists() {
+                    Config::from_file(path)
+                } else {
+                    Config::from_env()
+                }
+            }
+        }
+    }
+}
+
+impl RecordConfig {
+    /// full_domain
+    /// Joins this record's subdomain with its zone name into the fully
+    /// qualified domain name Cloudflare expects. An empty subdomain targets
+    /// the zone apex.
+    pub fn full_domain(&self, zone: &str) -> String {
+        if self.subdomain.is_empty() {
+            zone.to_string()
+        } else {
+            format!("{}.{}", self.subdomain, zone)
+        }
+    }
+}
@@ -0,0 +1,338 @@
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use log::{error, info};
+
+use crate::config::IpSource;
+
+pub const IPV4_SERVICES: &[&str] = &[
+    "https://api.ipify.org",
+    "https://ipv4.icanhazip.com",
+    "https://v4.ident.me",
+];
+
+pub const IPV6_SERVICES: &[&str] = &[
+    "https://api64.ipify.org",
+    "https://ipv6.icanhazip.com",
+    "https://v6.ident.me",
+];
+
+/* custom error type - when querying multiple providers we could have a disagreement or an error. */
+#[derive(Debug, PartialEq)]
+pub enum IpQueryError {
+    ConsensusMismatch {first: IpAddr, conflict: IpAddr},
+    NoIpAvailable {reason: &'static str},
+}
+
+/// query_ip_providers
+/// Queries every IP checking provider and tallies the address each one
+/// reports, tolerating individual failures. Returns the address with the
+/// most votes, provided it both meets `min_agreement` and isn't tied
+/// with the runner-up. Returns `ConsensusMismatch` on a genuine tie and
+/// `NoIpAvailable` if nothing met the threshold.
+fn query_ip_providers(
+    provider_client: &reqwest::blocking::Client,
+    providers: &[&str],
+    min_agreement: usize
+) -> Result<IpAddr, IpQueryError> {
+
+    let mut tally: HashMap<IpAddr, usize> = HashMap::new();
+
+    for &provider_url in providers {
+
+        info!("Querying provider {provider_url}");
+
+        /* a provider being unreachable shouldn't stop us from tallying the rest. */
+        let response = match provider_client.get(provider_url).send() {
+            Ok(resp) => resp,
+
+            /* this is an error that needs logging. */
+            Err(e) => {
+                error!("Error when querying {provider_url}: {:#?}", e);
+                continue;
+            },
+        };
+
+        let text = match response.text() {
+            Ok(t) => t,
+            Err(e) =>  {
+                error!("Error when parsing response from {provider_url}: {:#?}", e);
+                continue;
+            },
+        };
+
+        let new_ip = match text
+            .trim()
+            .parse::<std::net::IpAddr>() {
+            Ok(ip) => ip,
+            /* similarly here we need to error log */
+            Err(e) => {
+                error!("Response was not parseable as IpAddr ({text}): {:#?}", e);
+                continue;
+            }
+        };
+
+        *tally.entry(new_ip).or_insert(0) += 1;
+    }
+
+    resolve_majority(tally, min_agreement)
+}
+
+/// resolve_majority
+/// Pure tally/threshold decision used by `query_ip_providers`, split out so
+/// it can be unit tested without a network round trip. Picks the address
+/// with the most votes, provided it both meets `min_agreement`
+/// and isn't tied with the runner-up. Returns `ConsensusMismatch` on a
+/// genuine tie and `NoIpAvailable` if nothing met the threshold.
+fn resolve_majority(
+    tally: HashMap<IpAddr, usize>,
+    min_agreement: usize
+) -> Result<IpAddr, IpQueryError> {
+    let mut by_votes: Vec<(IpAddr, usize)> = tally.into_iter().collect();
+    by_votes.sort_by_key(|&(_, votes)| std::cmp::Reverse(votes));
+
+    let Some(&(winner, winner_votes)) = by_votes.first() else {
+        return Err(IpQueryError::NoIpAvailable { reason: "No providers were reachable." });
+    };
+
+    /* a genuine split - the top two addresses are equally well attested - is not
+    something we can resolve on our own, so we refuse to act on it. */
+    if let Some(&(runner_up, runner_up_votes)) = by_votes.get(1)
+        && runner_up_votes == winner_votes {
+        return Err(IpQueryError::ConsensusMismatch { first: winner, conflict: runner_up });
+    }
+
+    if winner_votes < min_agreement {
+        return Err(IpQueryError::NoIpAvailable { reason: "No address met the minimum agreement threshold." });
+    }
+
+    Ok(winner)
+}
+
+/// query_with_retries
+/// Wrapper around query_ip_providers that implements a retry mechanism.
+/// Attempts to query providers up to the specified number of retries.
+/// Returns None if all retry attempts fail.
+fn query_with_retries(
+    provider_client: &reqwest::blocking::Client,
+    providers: &[&str],
+    retries: usize,
+    min_agreement: usize
+) -> Option<IpAddr> {
+
+    for _ in 0..retries {
+        let result = query_ip_providers(provider_client, providers, min_agreement);
+
+        if let Ok(addr) = result {
+            return Some(addr);
+        }
+
+        let err = result.unwrap_err();
+        error!("Error during query, {:?}, retrying", err);
+
+        match err {
+            IpQueryError::ConsensusMismatch{first, conflict} => {
+                error!("Encountered consensus issue, {conflict} disagrees with {first}");
+            },
+            IpQueryError::NoIpAvailable{reason} => {
+                error!("Couldn't collect ip from providers: {reason}");
+            }
+        }
+    }
+
+    None
+}
+
+/// is_global_ipv4
+/// Returns true if the address is plausibly a host's real public IPv4
+/// address - i.e. not private (RFC1918), loopback, link-local, or broadcast.
+fn is_global_ipv4(ip: &Ipv4Addr) -> bool {
+    !ip.is_private()
+        && !ip.is_loopback()
+        && !ip.is_link_local()
+        && !ip.is_broadcast()
+        && !ip.is_unspecified()
+}
+
+/// is_global_ipv6
+/// Returns true if the address is plausibly a host's real public IPv6
+/// address - i.e. not loopback, link-local (`fe80::/10`), or unique-local
+/// (`fc00::/7`).
+fn is_global_ipv6(ip: &Ipv6Addr) -> bool {
+    let segments = ip.segments();
+    let is_link_local = (segments[0] & 0xffc0) == 0xfe80;
+    let is_unique_local = (segments[0] & 0xfe00) == 0xfc00;
+
+    !ip.is_loopback() && !is_link_local && !is_unique_local && !ip.is_unspecified()
+}
+
+/// interface_ipv4
+/// Reads the addresses bound to the named local interface and returns the
+/// first IPv4 address found, if any. Addresses are required to be globally
+/// routable unless `allow_private` opts back into RFC1918/loopback/etc.
+fn interface_ipv4(name: &str, allow_private: bool) -> Option<Ipv4Addr> {
+    let addrs = if_addrs::get_if_addrs().ok()?;
+
+    addrs.into_iter()
+        .filter(|iface| iface.name == name)
+        .filter_map(|iface| match iface.addr {
+            if_addrs::IfAddr::V4(v4) => Some(v4.ip),
+            if_addrs::IfAddr::V6(_) => None,
+        })
+        .find(|ip| allow_private || is_global_ipv4(ip))
+}
+
+/// interface_ipv6
+/// Reads the addresses bound to the named local interface and returns the
+/// first IPv6 address found, if any. Addresses are required to be globally
+/// routable (not link-local or unique-local) unless `allow_private` opts
+/// back into them.
+fn interface_ipv6(name: &str, allow_private: bool) -> Option<Ipv6Addr> {
+    let addrs = if_addrs::get_if_addrs().ok()?;
+
+    addrs.into_iter()
+        .filter(|iface| iface.name == name)
+        .filter_map(|iface| match iface.addr {
+            if_addrs::IfAddr::V6(v6) => Some(v6.ip),
+            if_addrs::IfAddr::V4(_) => None,
+        })
+        .find(|ip| allow_private || is_global_ipv6(ip))
+}
+
+/// resolve_ipv4
+/// Determines the current public IPv4 address via the configured source -
+/// either a reflector consensus query or a direct read from a local
+/// interface.
+fn resolve_ipv4(
+    source: &IpSource,
+    provider_client: &reqwest::blocking::Client,
+    retries: usize,
+    min_agreement: usize
+) -> Option<Ipv4Addr> {
+    match source {
+        IpSource::Reflector => query_with_retries(provider_client, IPV4_SERVICES, retries, min_agreement)
+            .and_then(|ip| match ip {
+                IpAddr::V4(v4) => Some(v4),
+                IpAddr::V6(_) => None,
+            }),
+        IpSource::Interface { name, allow_private } => interface_ipv4(name, *allow_private),
+    }
+}
+
+/// resolve_ipv6
+/// Determines the current public IPv6 address via the configured source -
+/// either a reflector consensus query or a direct read from a local
+/// interface.
+fn resolve_ipv6(
+    source: &IpSource,
+    provider_client: &reqwest::blocking::Client,
+    retries: usize,
+    min_agreement: usize
+) -> Option<Ipv6Addr> {
+    match source {
+        IpSource::Reflector => query_with_retries(provider_client, IPV6_SERVICES, retries, min_agreement)
+            .and_then(|ip| match ip {
+                IpAddr::V4(_) => None,
+                IpAddr::V6(v6) => Some(v6),
+            }),
+        IpSource::Interface { name, allow_private } => interface_ipv6(name, *allow_private),
+    }
+}
+
+/// get_supported_public_ips
+/// Determines the current public IPv4 and IPv6 addresses according to each
+/// version's configured `IpSource`. Returns a tuple of
+/// (Option<Ipv4Addr>, Option<Ipv6Addr>) where either or both may be None if
+/// the respective IP version is not available.
+pub fn get_supported_public_ips(
+    provider_client: &reqwest::blocking::Client,
+    v4_source: &IpSource,
+    v6_source: &IpSource,
+    retries: usize,
+    min_agreement: usize
+) -> (Option<Ipv4Addr>, Option<Ipv6Addr>) {
+
+    let ipv4 = resolve_ipv4(v4_source, provider_client, retries, min_agreement);
+    let ipv6 = resolve_ipv6(v6_source, provider_client, retries, min_agreement);
+
+    info!("Acquired IP addresses: v4: {:?}, v6: {:?}", ipv4, ipv6);
+    (ipv4, ipv6)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tally(votes: &[(&str, usize)]) -> HashMap<IpAddr, usize> {
+        votes.iter()
+            .map(|&(ip, count)| (ip.parse().unwrap(), count))
+            .collect()
+    }
+
+    #[test]
+    fn clean_majority_wins() {
+        let result = resolve_majority(tally(&[("1.1.1.1", 2), ("2.2.2.2", 1)]), 2);
+        assert_eq!(result, Ok("1.1.1.1".parse().unwrap()));
+    }
+
+    #[test]
+    fn three_way_tie_is_a_consensus_mismatch() {
+        let result = resolve_majority(tally(&[("1.1.1.1", 1), ("2.2.2.2", 1), ("3.3.3.3", 1)]), 1);
+        assert!(matches!(result, Err(IpQueryError::ConsensusMismatch { .. })));
+    }
+
+    #[test]
+    fn below_threshold_is_rejected() {
+        let result = resolve_majority(tally(&[("1.1.1.1", 1)]), 2);
+        assert_eq!(result, Err(IpQueryError::NoIpAvailable { reason: "No address met the minimum agreement threshold." }));
+    }
+
+    #[test]
+    fn single_responder_meeting_threshold_wins() {
+        let result = resolve_majority(tally(&[("1.1.1.1", 1)]), 1);
+        assert_eq!(result, Ok("1.1.1.1".parse().unwrap()));
+    }
+
+    #[test]
+    fn no_responders_is_rejected() {
+        let result = resolve_majority(HashMap::new(), 1);
+        assert_eq!(result, Err(IpQueryError::NoIpAvailable { reason: "No providers were reachable." }));
+    }
+
+    #[test]
+    fn is_global_ipv4_cases() {
+        let cases: &[(&str, bool)] = &[
+            ("8.8.8.8", true),
+            ("1.1.1.1", true),
+            ("192.168.1.1", false),
+            ("10.0.0.1", false),
+            ("172.16.0.1", false),
+            ("127.0.0.1", false),
+            ("169.254.1.1", false),
+            ("255.255.255.255", false),
+            ("0.0.0.0", false),
+        ];
+
+        for &(ip, expected) in cases {
+            let addr: Ipv4Addr = ip.parse().unwrap();
+            assert_eq!(is_global_ipv4(&addr), expected, "{ip} should be global: {expected}");
+        }
+    }
+
+    #[test]
+    fn is_global_ipv6_cases() {
+        let cases: &[(&str, bool)] = &[
+            ("2001:4860:4860::8888", true),
+            ("::1", false),
+            ("fe80::1", false),
+            ("fc00::1", false),
+            ("fd12:3456:789a::1", false),
+            ("::", false),
+        ];
+
+        for &(ip, expected) in cases {
+            let addr: Ipv6Addr = ip.parse().unwrap();
+            assert_eq!(is_global_ipv6(&addr), expected, "{ip} should be global: {expected}");
+        }
+    }
+}
@@ -1,34 +1,30 @@
+use anyhow::Context;
 use dotenvy::dotenv;
 use reqwest::header::{AUTHORIZATION, CONTENT_TYPE, HeaderMap, HeaderValue};
 use reqwest::blocking::{Client};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use core::time;
+use signal_hook::consts::{SIGINT, SIGTERM};
+use signal_hook::flag;
 use std::time::Duration;
 use std::{env};
 use std::collections::{HashSet, HashMap};
-use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::net::IpAddr;
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use std::thread::sleep;
 use log::{info, warn, error};
 
-const IPV4_SERVICES: &[&str] = &[
-    "https://api.ipify.org", 
-    "https://ipv4.icanhazip.com",
-    "https://v4.ident.me",
-];
+mod cli;
+mod config;
+mod ip;
 
-const IPV6_SERVICES: &[&str] = &[
-    "https://api64.ipify.org",
-    "https://ipv6.icanhazip.com",
-    "https://v6.ident.me",
-];
-
-/* custom error type - when querying multiple providers we could have a disagreement or an error. */
-#[derive(Debug)]
-enum IpQueryError {
-    ConsensusMismatch {first: IpAddr, conflict: IpAddr},
-    NoIpAvailable {reason: &'static str},
-}
+use clap::Parser;
+use cli::{Cli, Command};
+use config::{Config, RecordConfig};
+use ip::get_supported_public_ips;
+use tabled::{Table, Tabled};
 
 #[derive(Debug)]
 enum CloudflareAPIError {
@@ -40,6 +36,21 @@ enum CloudflareAPIError {
     ResponseError {message: String}
 }
 
+impl std::fmt::Display for CloudflareAPIError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CloudflareAPIError::ConnectionError { url, err } => write!(f, "Failed to connect to {url}: {err}"),
+            CloudflareAPIError::MissingDomain { domain } => write!(f, "{domain} is not registered with Cloudflare"),
+            CloudflareAPIError::ResponseParseError => write!(f, "Failed to parse Cloudflare's response as JSON"),
+            CloudflareAPIError::JsonParseError { json } => write!(f, "Failed to parse Cloudflare's response into the expected shape: {json}"),
+            CloudflareAPIError::JsonFormatError { json } => write!(f, "Cloudflare's response was missing an expected field: {json}"),
+            CloudflareAPIError::ResponseError { message } => write!(f, "{message}"),
+        }
+    }
+}
+
+impl std::error::Error for CloudflareAPIError {}
+
 #[derive(Debug, Serialize, Deserialize)]
 struct DnsRecord {
     pub id: String,
@@ -74,7 +85,7 @@ struct DnsRecordUpdate {
 /// split_subdomain
 /// Parses a comma-separated string of subdomains into a HashSet.
 /// Returns an empty HashSet if the input string is empty.
-fn split_subdomain(subdomains: &str) -> HashSet<&str> {
+pub(crate) fn split_subdomain(subdomains: &str) -> HashSet<&str> {
     if subdomains.is_empty() {
         HashSet::new()
     } else {
@@ -82,140 +93,27 @@ fn split_subdomain(subdomains: &str) -> HashSet<&str> {
     }
 }
 
-/// query_ip_providers
-/// Queries multiple IP checking providers to determine the current public IP address.
-/// Requires consensus among all reachable providers to return a successful result.
-/// Returns an error if providers disagree or if none are reachable.
-fn query_ip_providers(
-    provider_client: &reqwest::blocking::Client,
-    providers: &[&str]
-) -> Result<IpAddr, IpQueryError> {
-
-    let mut addr: Option<IpAddr> = None;
-
-    let min_agreement = 2;
-    let mut n_agreement = 0;
-
-
-    for &provider_url in providers {
-
-        info!("Querying provider {provider_url}");
-
-        /* in a case where a provider is unreachable, just abort. */
-        let response = match provider_client.get(provider_url).send() {
-            Ok(resp) => resp,
-
-            /* this is an error that needs logging. */
-            Err(e) => {
-                error!("Error when querying {provider_url}: {:#?}", e);
-                continue;
-            },
-        };
-
-        let text = match response.text() {
-            Ok(t) => t,
-            Err(e) =>  {
-                error!("Error when parsing response from {provider_url}: {:#?}", e);
-                continue;
-            },
-        };
-
-        let new_ip = match text
-            .trim()
-            .parse::<std::net::IpAddr>() {
-            Ok(ip) => ip,
-            /* similarly here we need to error log */
-            Err(e) => {
-                error!("Response was not parseable as IpAddr ({text}): {:#?}", e);
-                continue;
-            }
-        };
-
-        /* we must check for consensus, otherwise something is going wrong! */
-        match addr {
-            Some(current) => {
-                if current != new_ip {
-                    return Err(IpQueryError::ConsensusMismatch{
-                        first: current,
-                        conflict: new_ip,
-                    });
-                }             
-                n_agreement += 1;
-            },
-            None => {
-                addr = Some(new_ip);
-                n_agreement += 1;
-            }
-        } 
-    }
-
-    if let Some(ip) = addr && n_agreement > min_agreement {
-        return Ok(ip);
-    }
-
-    return Err(IpQueryError::NoIpAvailable { reason: "No providers were reachable." });
-}
-
-/// query_with_retries
-/// Wrapper around query_ip_providers that implements a retry mechanism.
-/// Attempts to query providers up to the specified number of retries.
-/// Returns None if all retry attempts fail.
-fn query_with_retries(
-    provider_client: &reqwest::blocking::Client,
-    providers: &[&str], 
-    retries: usize
-) -> Option<IpAddr> {
-
-    for _ in 0..retries {
-        let result = query_ip_providers(provider_client, providers);
-
-        if let Ok(addr) = result {
-            return Some(addr);
-        }
-                
-        let err = result.unwrap_err();
-        error!("Error during query, {:?}, retrying", err);
-
-        match err {
-            IpQueryError::ConsensusMismatch{first, conflict} => {
-                error!("Encountered consensus issue, {conflict} disagrees with {first}");
-            },
-            IpQueryError::NoIpAvailable{reason} => {
-                error!("Couldn't collect ip from providers: {reason}");
-            }
-        }
-    }
-
-    return None
-}
-
-/// get_supported_public_ips
-/// Queries both IPv4 and IPv6 provider lists to determine supported public IP addresses.
-/// Returns a tuple of (Option<Ipv4Addr>, Option<Ipv6Addr>) where either or both may be None
-/// if the respective IP version is not available or consensus cannot be reached.
-fn get_supported_public_ips(
-    provider_client: &reqwest::blocking::Client,
-    v4_providers: &[&str], 
-    v6_providers: &[&str], 
-    retries: usize
-) -> (Option<Ipv4Addr>, Option<Ipv6Addr>) {
-
-    let ipv4 = query_with_retries(provider_client, v4_providers, retries)
-        .and_then(|ip| match ip {
-            IpAddr::V4(v4) => Some(v4),
-            IpAddr::V6(_) => None,
-        }
-    );
+/// build_cloudflare_client
+/// Builds a `reqwest` blocking client pre-configured with the Cloudflare
+/// bearer token and JSON content-type headers for a single zone's API token.
+fn build_cloudflare_client(token: &str) -> anyhow::Result<Client> {
+    let auth_value = HeaderValue::from_str(&format!("Bearer {token}"))
+        .context("Token must not contain invalid header characters")?;
 
-    let ipv6 = query_with_retries(provider_client, v6_providers, retries)
-        .and_then(|ip| match ip {
-            IpAddr::V4(_) => None,
-            IpAddr::V6(v6) => Some(v6),
-        }
-    );
+    let cfclient_headers = HeaderMap::from_iter([
+        (AUTHORIZATION, auth_value),
+        (
+            CONTENT_TYPE,
+            HeaderValue::from_static("application/json")
+        )
+    ]);
 
-    info!("Acquired IP addresses: v4: {:?}, v6: {:?}", ipv4, ipv6);
-    (ipv4, ipv6)
+    Client::builder()
+        .default_headers(cfclient_headers)
+        .timeout(Duration::from_secs(10))
+        .connect_timeout(Duration::from_secs(5))
+        .build()
+        .context("Failed to build the Cloudflare HTTP client")
 }
 
 /// cloudflare_get_zone_id
@@ -231,7 +129,7 @@ fn cloudflare_get_zone_id(
     let url = format!("{apibase}/zones?name={domain}");
 
     let response = cloudflare_client.get(&url).send()
-        .map_err(|e| CloudflareAPIError::ConnectionError { url: url, err: (e) })?;
+        .map_err(|e| CloudflareAPIError::ConnectionError { url, err: e })?;
 
     let json: Value = response.json()
         .map_err(|_| CloudflareAPIError::ResponseParseError)?;
@@ -282,7 +180,7 @@ fn cloudflare_get_dns_record_id(
     let url = format!("{apibase}/zones/{zone_id}/dns_records");
 
     let response = cloudflare_client.get(&url).send()
-        .map_err(|e| CloudflareAPIError::ConnectionError { url: url, err: e })?;
+        .map_err(|e| CloudflareAPIError::ConnectionError { url, err: e })?;
 
     let json: serde_json::Value = response.json()
         .map_err(|_| CloudflareAPIError::ResponseParseError)?;
@@ -317,30 +215,38 @@ fn cloudflare_get_dns_record_id(
     Ok(dns_records)
 }
 
+/// DesiredRecord
+/// The desired state for a DNS record update or creation: the address it
+/// should point at plus its TTL and proxied flag. Bundles the fields that
+/// used to be threaded through individually as separate parameters.
+#[derive(Debug, Clone, Copy)]
+struct DesiredRecord {
+    ip: IpAddr,
+    ttl: u32,
+    proxied: bool,
+}
+
 /// create_update_records_from_ip_set
 /// Generates a DnsRecordUpdate struct for either an A (IPv4) or AAAA (IPv6) record.
 /// The record type is determined automatically based on the IP address type.
 /// Used to prepare record data for Cloudflare API update or create operations.
-fn generate_dns_record(
-    ip: &IpAddr,
-    full_name: String,
-    ttl: u32
-) -> DnsRecordUpdate {
+fn generate_dns_record(full_name: String, desired: DesiredRecord) -> DnsRecordUpdate {
+    let DesiredRecord { ip, ttl, proxied } = desired;
 
     match ip {
         IpAddr::V4(v4) => DnsRecordUpdate{
             record_type: "A".to_string(),
             name: full_name,
             content: v4.to_string(),
-            ttl: ttl,
-            proxied: false
+            ttl,
+            proxied
         },
         IpAddr::V6(v6) => DnsRecordUpdate{
             record_type: "AAAA".to_string(),
             name: full_name,
             content: v6.to_string(),
-            ttl: ttl,
-            proxied: false
+            ttl,
+            proxied
         }
     }
 }
@@ -354,12 +260,11 @@ fn cloudflare_create_new_dns_record(
     cloudflare_client: &Client,
     full_domain: &str,
     zone_id: &str,
-    ip: &IpAddr,
-    ttl: u32
+    desired: DesiredRecord
 ) -> Result<String, CloudflareAPIError> {
     let url = format!("{apibase}/zones/{zone_id}/dns_records");
 
-    let update_record = generate_dns_record(&ip, full_domain.to_string(), ttl);
+    let update_record = generate_dns_record(full_domain.to_string(), desired);
 
     let response = cloudflare_client
         .post(&url)
@@ -403,12 +308,11 @@ fn cloudflare_update_dns_record(
     full_domain: &str,
     zone_id: &str,
     record_id: &str,
-    ip: &IpAddr,
-    ttl: u32
+    desired: DesiredRecord
 ) -> Result<bool, CloudflareAPIError> {
     let url = format!("{apibase}/zones/{zone_id}/dns_records/{record_id}");
 
-    let update_record = generate_dns_record(&ip, full_domain.to_string(), ttl);
+    let update_record = generate_dns_record(full_domain.to_string(), desired);
 
     let response = cloudflare_client
         .patch(&url)
@@ -435,10 +339,9 @@ fn cloudflare_update_dns_record(
 /// only if a new record was created. Logs errors if the operation fails.
 fn update_or_create_record(
     apibase: &str,
-    cloudflare_client: &Client, 
-    full_domain: &str, 
-    ip: &IpAddr, 
-    ttl: u32,
+    cloudflare_client: &Client,
+    full_domain: &str,
+    desired: DesiredRecord,
     zone_id: &str,
     record_id: Option<&String>
 ) -> (bool, Option<String>) {
@@ -446,163 +349,201 @@ fn update_or_create_record(
         Some(id) => {
             let result = cloudflare_update_dns_record(
                 apibase,
-                cloudflare_client, 
+                cloudflare_client,
                 full_domain,
-                zone_id, 
-                &id, 
-                ip, 
-                ttl
+                zone_id,
+                id,
+                desired
             );
 
             if let Ok(success) = result {
                 if ! success {
                     error!("Update returned success=false.")
-                } 
+                }
                 /* We sent the update request - now we see if it failed or not. */
-                return (success, None);
+                (success, None)
             } else {
                 error!("Error when updating. Encountered {:#?}", result.unwrap_err());
-                return (false, None);
+                (false, None)
             }
 
         },
         /* no record exists - we must create it. */
         None => {
             let result = cloudflare_create_new_dns_record(
-                apibase, 
-                cloudflare_client, 
-                full_domain, 
-                zone_id, 
-                ip, 
-                ttl
+                apibase,
+                cloudflare_client,
+                full_domain,
+                zone_id,
+                desired
             );
 
             if let Ok(id) = result {
-                return (true, Some(id));
+                (true, Some(id))
             } else {
                 error!("Error when creating record. Encountered {:#?}", result.unwrap_err());
-                return (false, None);
+                (false, None)
             }
         }
     }
 }
 
+/// CachedRecord
+/// The last-known state of a DNS record this daemon manages: its Cloudflare
+/// record ID plus the fields we compare against the desired state to decide
+/// whether an update is actually needed.
+#[derive(Debug, Clone)]
+struct CachedRecord {
+    id: String,
+    content: String,
+    proxied: bool,
+    ttl: u32,
+}
+
+/// record_is_up_to_date
+/// Returns true if a cached record's content/proxied/ttl already match the
+/// desired state, meaning no Cloudflare API call is needed.
+fn record_is_up_to_date(cached: &CachedRecord, desired: &DesiredRecord) -> bool {
+    cached.content == desired.ip.to_string() && cached.proxied == desired.proxied && cached.ttl == desired.ttl
+}
+
 /// update_dns_record
-/// High-level wrapper that handles DNS record updates and caches record IDs.
-/// Automatically determines whether to update or create a record based on the cache.
-/// Updates the known_ids HashMap with new record IDs when records are created.
-/// Returns true if the operation succeeded, false otherwise.
+/// High-level wrapper that handles DNS record updates and caches record state.
+/// Automatically determines whether to update or create a record based on the cache,
+/// and skips the API call entirely if the cached content/proxied/ttl already match
+/// the desired state. Updates the known_ids HashMap with the resulting record state.
+/// Returns true if the record is already up to date or the operation succeeded.
 fn update_dns_record(
     apibase: &str,
     client: &Client,
     domain: &str,
-    ip: &IpAddr,
+    desired: DesiredRecord,
     record_type: &str,
-    ttl: u32,
     zone_id: &str,
-    known_ids: &mut HashMap<(String, String), String>,
+    known_ids: &mut HashMap<(String, String), CachedRecord>,
 ) -> bool {
+    let DesiredRecord { ip, ttl, proxied } = desired;
+
     let key = (domain.to_string(), record_type.to_string());
-    let record_id = known_ids.get(&key);
-    
-    let (success, new_id) = update_or_create_record(
-        apibase, client, domain, &ip, ttl, zone_id, record_id
-    );
-    
-    if let Some(id) = new_id {
-        known_ids.insert(key, id);
-    }
-    
-    if success {
-        info!("Updated {} to {}", domain, ip);
-    } else {
-        error!("Error encountered while updating {}. No changes made.", domain);
-    }
-    
-    success
-} 
+    let cached = known_ids.get(&key);
 
-fn main() {
-    dotenv().ok();
-    env_logger::init();
+    let desired_content = ip.to_string();
 
-    log::info!("Beginning execution");
+    if let Some(cached) = cached
+        && record_is_up_to_date(cached, &desired) {
+        info!("{} is already up to date ({}), skipping.", domain, desired_content);
+        return true;
+    }
 
-    let ttl: u32 = 600;
-    let sleep_time: u64 = 600;
+    let record_id = cached.map(|cached| &cached.id);
 
-    let apibase = env::var("APIBASE")
-        .expect("APIBASE must be set");
+    let (success, new_id) = update_or_create_record(
+        apibase, client, domain, desired, zone_id, record_id
+    );
 
-    let domain = env::var("DOMAIN")
-        .expect("DOMAIN must be set");
+    if success {
+        let id = new_id.or_else(|| cached.map(|cached| cached.id.clone()));
 
-    let env_subdomains = env::var("SUBDOMAINS")
-        .expect("SUBDOMAINS must be set");
+        if let Some(id) = id {
+            known_ids.insert(key, CachedRecord { id, content: desired_content, proxied, ttl });
+        }
 
-    /* subdomains can be blank, or it can be an array of strings */
-    let subdomains = split_subdomain(
-        &env_subdomains
-    );
+        info!("Updated {} to {}", domain, ip);
+    } else {
+        error!("Error encountered while updating {}. No changes made.", domain);
+    }
 
-    let token = env::var("TOKEN")
-        .expect("TOKEN must be set");
+    success
+}
 
-    info!("Environment variables read. Subdomains are: {:#?}", subdomains);
+/// ZoneRuntime
+/// Resolved, ready-to-poll state for a single configured zone: its own
+/// Cloudflare client (bound to the zone's token), its resolved zone ID, the
+/// records to keep up to date, and the DNS record ID cache for that zone.
+struct ZoneRuntime {
+    zone: String,
+    zone_id: String,
+    client: Client,
+    records: Vec<RecordConfig>,
+    known_dns_ids: HashMap<(String, String), CachedRecord>,
+}
 
-    let cfclient_headers = HeaderMap::from_iter([
-        (
-            AUTHORIZATION,
-            HeaderValue::from_str(&format!("Bearer {token}"))
-                .expect("Token must not contain invalid chars.")
-        ),
-        (
-            CONTENT_TYPE, 
-            HeaderValue::from_static("application/json")
-        )
-    ]);
+/// build_zone_runtime
+/// Resolves a single `ZoneConfig` into a `ZoneRuntime`, logging and skipping
+/// the zone (rather than aborting the whole daemon) if anything in
+/// `try_build_zone_runtime` fails.
+fn build_zone_runtime(apibase: &str, zone_config: config::ZoneConfig) -> Option<ZoneRuntime> {
+    let zone_name = zone_config.zone.clone();
 
-    let cloudflare_client = reqwest::blocking::Client::builder()
-        .default_headers(cfclient_headers)
-        .timeout(Duration::from_secs(10))
-        .connect_timeout(Duration::from_secs(5))
-        .build()
-        .expect("The client should be able to build.");
+    match try_build_zone_runtime(apibase, zone_config) {
+        Ok(runtime) => Some(runtime),
+        Err(e) => {
+            error!("Skipping zone {zone_name}: {e:#}");
+            None
+        }
+    }
+}
 
-    /* next, collect the relevant info from cloudflare's api so we can modify the records. */
-    let zone_id = match cloudflare_get_zone_id(&apibase, &cloudflare_client, &domain) {
-        Ok(zone) => zone,
+/// try_build_zone_runtime
+/// Builds this zone's Cloudflare client, resolves its zone ID (treating
+/// `zone` as a pre-resolved ID if it isn't a bare domain name Cloudflare
+/// recognizes, and then looking up that zone's name), and pre-populates the known DNS record ID cache for every
+/// configured record.
+fn try_build_zone_runtime(apibase: &str, zone_config: config::ZoneConfig) -> anyhow::Result<ZoneRuntime> {
+    let client = build_cloudflare_client(&zone_config.token)
+        .with_context(|| format!("Failed to build a Cloudflare client for {}", zone_config.zone))?;
+
+    let (zone_id, zone_name) = match cloudflare_get_zone_id(apibase, &client, &zone_config.zone) {
+        Ok(zone_id) => (zone_id, zone_config.zone.clone()),
+        Err(CloudflareAPIError::MissingDomain { .. }) => {
+            /* `zone` may already be a pre-resolved zone ID rather than a domain name,
+            in which case we still need the zone's name to build record names. */
+            info!("{} is not a domain name Cloudflare resolved - treating it as a zone ID.", zone_config.zone);
+            let url = format!("{apibase}/zones/{}", zone_config.zone);
+            let json: Value = client.get(&url).send()
+                .and_then(|response| response.json())
+                .with_context(|| format!("Failed to look up zone {}", zone_config.zone))?;
+            let zone_name = json.get("result")
+                .and_then(|result| result.get("name"))
+                .and_then(|name| name.as_str())
+                .with_context(|| format!("{} is neither a domain name nor a zone ID Cloudflare recognizes", zone_config.zone))?;
+            (zone_config.zone.clone(), zone_name.to_string())
+        },
         Err(e) => {
-            error!("Something went wrong: {:#?}", e);
-            panic!("Abort.");
+            return Err(e).with_context(|| format!("Failed to resolve zone for {}", zone_config.zone));
         }
     };
 
-    info!("Collected zone_id from Cloudflare for {domain}: {zone_id}");
+    info!("Collected zone_id from Cloudflare for {}: {zone_id}", zone_config.zone);
 
-    let mut known_dns_ids: HashMap<(String, String), String> = HashMap::new();
+    let mut known_dns_ids: HashMap<(String, String), CachedRecord> = HashMap::new();
 
-    /* we pre-populate the ids in the hashmap for each subdomain. */
-    for subdomain in subdomains.clone() {
-        let full_domain = format!("{subdomain}.{domain}");
+    /* we pre-populate the ids and state in the hashmap for each configured record. */
+    for record in &zone_config.records {
+        let full_domain = record.full_domain(&zone_name);
 
         info!("Checking for subdomain {full_domain}...");
 
-        if let Ok(records) = cloudflare_get_dns_record_id(
-            &apibase.as_str(), 
-            &cloudflare_client, 
+        if let Ok(dns_records) = cloudflare_get_dns_record_id(
+            apibase,
+            &client,
             full_domain.as_str(),
             zone_id.as_str()) {
-            
+
             /* we may get back one or two records */
-            for record in records {
-                info!("\tCaching {0} record: {1}", record.record_type, record.id);
+            for dns_record in dns_records {
+                info!("\tCaching {0} record: {1}", dns_record.record_type, dns_record.id);
                 known_dns_ids.insert(
                     (
-                        full_domain.clone(), 
-                        record.record_type.clone()
-                    ), 
-                    record.id
+                        full_domain.clone(),
+                        dns_record.record_type.clone()
+                    ),
+                    CachedRecord {
+                        id: dns_record.id,
+                        content: dns_record.content,
+                        proxied: dns_record.proxied,
+                        ttl: dns_record.ttl,
+                    }
                 );
             }
         } else {
@@ -610,6 +551,114 @@ fn main() {
         }
     }
 
+    Ok(ZoneRuntime {
+        zone: zone_name,
+        zone_id,
+        client,
+        records: zone_config.records,
+        known_dns_ids,
+    })
+}
+
+/// RecordRow
+/// A single row of the table printed by `cfddns list`: what we have cached
+/// for one (zone, record) pair.
+#[derive(Tabled)]
+struct RecordRow {
+    #[tabled(rename = "Zone")]
+    zone: String,
+    #[tabled(rename = "Name")]
+    name: String,
+    #[tabled(rename = "Type")]
+    record_type: String,
+    #[tabled(rename = "Content")]
+    content: String,
+    #[tabled(rename = "TTL")]
+    ttl: u32,
+    #[tabled(rename = "Proxied")]
+    proxied: bool,
+    #[tabled(rename = "Record ID")]
+    id: String,
+}
+
+/// list
+/// Resolves every configured zone and prints a table of the DNS records
+/// Cloudflare currently has for each configured record. Gives users a quick
+/// way to inspect what the daemon sees without reading raw logs.
+fn list(apibase: &str, config: Config) -> anyhow::Result<()> {
+    let mut rows: Vec<RecordRow> = Vec::new();
+
+    for zone_config in config.zones {
+        let zone_name = zone_config.zone.clone();
+
+        if let Some(zone) = build_zone_runtime(apibase, zone_config) {
+            let mut zone_rows: Vec<RecordRow> = zone.known_dns_ids
+                .into_iter()
+                .map(|((name, record_type), cached)| RecordRow {
+                    zone: zone_name.clone(),
+                    name,
+                    record_type,
+                    content: cached.content,
+                    ttl: cached.ttl,
+                    proxied: cached.proxied,
+                    id: cached.id,
+                })
+                .collect();
+
+            zone_rows.sort_by(|a, b| (&a.name, &a.record_type).cmp(&(&b.name, &b.record_type)));
+            rows.append(&mut zone_rows);
+        }
+    }
+
+    println!("{}", Table::new(rows));
+    Ok(())
+}
+
+/// sleep_with_shutdown_check
+/// Sleeps in short increments instead of one long call, so a shutdown signal
+/// received mid-sleep is noticed promptly instead of after the full cooldown.
+/// Returns true if a shutdown was requested while sleeping.
+fn sleep_with_shutdown_check(duration: Duration, shutdown: &AtomicBool) -> bool {
+    const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+    let mut remaining = duration;
+
+    while remaining > Duration::ZERO {
+        if shutdown.load(Ordering::Relaxed) {
+            return true;
+        }
+
+        let chunk = remaining.min(POLL_INTERVAL);
+        sleep(chunk);
+        remaining -= chunk;
+    }
+
+    shutdown.load(Ordering::Relaxed)
+}
+
+/// run
+/// Runs the long-running update daemon: resolves every configured zone, then
+/// loops polling for public IP changes and pushing them to Cloudflare until a
+/// SIGTERM/SIGINT requests a clean shutdown.
+fn run(apibase: &str, config: Config) -> anyhow::Result<()> {
+    log::info!("Beginning execution");
+
+    let sleep_time: u64 = 600;
+
+    info!("Config loaded. {} zone(s) configured.", config.zones.len());
+
+    let ip_sources = config.ip_sources;
+
+    /* next, collect the relevant info from cloudflare's api so we can modify the records. */
+    let mut zones: Vec<ZoneRuntime> = config.zones
+        .into_iter()
+        .filter_map(|zone_config| build_zone_runtime(apibase, zone_config))
+        .collect();
+
+    if zones.is_empty() {
+        anyhow::bail!("No zones could be resolved - check the config and Cloudflare tokens");
+    }
+
     /* TODO: maybe make this configurable later. */
     let retries = 5;
 
@@ -620,20 +669,27 @@ fn main() {
         .timeout(Duration::from_secs(10))
         .connect_timeout(Duration::from_secs(5))
         .build()
-        .expect("The client should be able to build.");
+        .context("Failed to build the IP-reflector HTTP client")?;
 
     let max_failures = 5;
     let mut failure_count = 0;
 
+    /* so a container orchestrator's SIGTERM (or a developer's Ctrl-C) breaks the
+    loop cleanly instead of killing us mid-update. */
+    let shutdown = Arc::new(AtomicBool::new(false));
+    flag::register(SIGTERM, Arc::clone(&shutdown)).context("Failed to register a SIGTERM handler")?;
+    flag::register(SIGINT, Arc::clone(&shutdown)).context("Failed to register a SIGINT handler")?;
+
     info!("Beginning update loop.");
 
-    loop {
+    while !shutdown.load(Ordering::Relaxed) {
         info!("Beginning cycle.");
 
         let mut cycle_failed: bool = false;
 
-        /* TODO: detect any changes on the network device (netlink, etc) */
-        let (ipv4, ipv6) = get_supported_public_ips(&ipquery_client, IPV4_SERVICES, IPV6_SERVICES, retries);
+        let (ipv4, ipv6) = get_supported_public_ips(
+            &ipquery_client, &ip_sources.v4, &ip_sources.v6, retries, ip_sources.min_agreement
+        );
 
         /* update v4 if needed. */
         let update_v4 = if ipv4_cache != ipv4 {
@@ -660,38 +716,41 @@ fn main() {
             info!("Cached IPs are still valid. No updates will occur this cycle.");
             cycle_failed = false;
         } else {
-            /* For each of our subdomains, we need to send separate records for each of A and AAAA. */
-            for subdomain in &subdomains {
-                let full_domain = format!("{subdomain}.{domain}");
-
-                /* v4 update */
-                if let Some(ip) = ipv4_cache && update_v4 {
-                    if ! update_dns_record(
-                        &apibase, 
-                        &cloudflare_client, 
-                        &full_domain, 
-                        &IpAddr::V4(ip), 
-                        "A",
-                        ttl, 
-                        &zone_id, 
-                        &mut known_dns_ids
-                    ) {
+            /* For each zone, and each of its configured records, we need to send
+            separate records for each of A and AAAA. */
+            for zone in &mut zones {
+                for record in &zone.records {
+                    let full_domain = record.full_domain(&zone.zone);
+
+                    /* v4 update */
+                    if let Some(ip) = ipv4_cache
+                        && update_v4 && record.a
+                        && ! update_dns_record(
+                            apibase,
+                            &zone.client,
+                            &full_domain,
+                            DesiredRecord { ip: IpAddr::V4(ip), ttl: record.ttl, proxied: record.proxied },
+                            "A",
+                            &zone.zone_id,
+                            &mut zone.known_dns_ids
+                        )
+                    {
                         cycle_failed = true;
                     }
-                }
 
-                /* v6 update */
-                if let Some(ip) = ipv6_cache && update_v6 {
-                    if ! update_dns_record(
-                        &apibase,
-                        &cloudflare_client, 
-                        &full_domain, 
-                        &IpAddr::V6(ip), 
-                        "AAAA", 
-                        ttl, 
-                        &zone_id,
-                        &mut known_dns_ids
-                    ) {
+                    /* v6 update */
+                    if let Some(ip) = ipv6_cache
+                        && update_v6 && record.aaaa
+                        && ! update_dns_record(
+                            apibase,
+                            &zone.client,
+                            &full_domain,
+                            DesiredRecord { ip: IpAddr::V6(ip), ttl: record.ttl, proxied: record.proxied },
+                            "AAAA",
+                            &zone.zone_id,
+                            &mut zone.known_dns_ids
+                        )
+                    {
                         cycle_failed = true;
                     }
                 }
@@ -712,15 +771,87 @@ fn main() {
         }
 
         /* exponential backoff - we may have a problem here! */
-        if failure_count > max_failures {
+        let cooldown = if failure_count > max_failures {
             warn!("Failures exceeded max failures - sleeping for 5 cycles.");
-            sleep(time::Duration::from_secs(sleep_time * 5));
+            Duration::from_secs(sleep_time * 5)
         } else {
             /* we only want to rest roughly as long as a record ttl,
-            since if our ip changes during that time, the cache will 
+            since if our ip changes during that time, the cache will
             probably have expired. */
             info!("Cycle finished, sleeping.");
-            sleep(time::Duration::from_secs(sleep_time));
+            Duration::from_secs(sleep_time)
+        };
+
+        if sleep_with_shutdown_check(cooldown, &shutdown) {
+            break;
         }
     }
+
+    info!("Shutdown signal received - exiting cleanly.");
+    Ok(())
+}
+
+/// Distinct nonzero exit status for unrecoverable startup errors (config load,
+/// client build, zone resolution), so container orchestrators and scripts can
+/// tell a config problem apart from a transient failure.
+const EXIT_CONFIG_ERROR: u8 = 78;
+
+fn main() -> ExitCode {
+    dotenv().ok();
+    env_logger::init();
+
+    match try_main() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            error!("{:#}", e);
+            ExitCode::from(EXIT_CONFIG_ERROR)
+        }
+    }
+}
+
+fn try_main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+
+    let apibase = env::var("APIBASE").context("APIBASE must be set")?;
+
+    let config = Config::load()
+        .context("Config must load from either a config file or the legacy environment variables")?;
+
+    match cli.command.unwrap_or(Command::Run) {
+        Command::Run => run(&apibase, config),
+        Command::List => list(&apibase, config),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cached(content: &str, proxied: bool, ttl: u32) -> CachedRecord {
+        CachedRecord { id: "record-id".to_string(), content: content.to_string(), proxied, ttl }
+    }
+
+    fn desired(ip: &str, ttl: u32, proxied: bool) -> DesiredRecord {
+        DesiredRecord { ip: ip.parse().unwrap(), ttl, proxied }
+    }
+
+    #[test]
+    fn matching_content_ttl_and_proxied_is_up_to_date() {
+        assert!(record_is_up_to_date(&cached("1.1.1.1", false, 600), &desired("1.1.1.1", 600, false)));
+    }
+
+    #[test]
+    fn changed_ip_is_not_up_to_date() {
+        assert!(!record_is_up_to_date(&cached("1.1.1.1", false, 600), &desired("2.2.2.2", 600, false)));
+    }
+
+    #[test]
+    fn changed_proxied_is_not_up_to_date() {
+        assert!(!record_is_up_to_date(&cached("1.1.1.1", false, 600), &desired("1.1.1.1", 600, true)));
+    }
+
+    #[test]
+    fn changed_ttl_is_not_up_to_date() {
+        assert!(!record_is_up_to_date(&cached("1.1.1.1", false, 600), &desired("1.1.1.1", 300, false)));
+    }
 }
\ No newline at end of file